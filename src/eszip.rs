@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use byteorder::{ByteOrder, LE};
+use sha2::{Digest, Sha256};
+
+use crate::types::BuildResult;
+
+/// Magic header identifying an eszip v2-style archive.
+const MAGIC: &[u8; 8] = b"ESZIP2\0\0";
+const HASH_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModuleKind {
+  Js,
+  Json,
+  Wasm,
+  /// Points at another entry by index instead of carrying its own data.
+  Redirect,
+}
+
+impl ModuleKind {
+  fn tag(self) -> u8 {
+    match self {
+      Self::Js => 0,
+      Self::Json => 1,
+      Self::Wasm => 2,
+      Self::Redirect => 3,
+    }
+  }
+
+  fn from_tag(tag: u8) -> Result<Self, EszipError> {
+    match tag {
+      0 => Ok(Self::Js),
+      1 => Ok(Self::Json),
+      2 => Ok(Self::Wasm),
+      3 => Ok(Self::Redirect),
+      tag => Err(EszipError::InvalidModuleKind(tag)),
+    }
+  }
+
+  fn from_path(path: &str) -> Self {
+    if path.ends_with(".json") {
+      Self::Json
+    } else if path.ends_with(".wasm") {
+      Self::Wasm
+    } else {
+      Self::Js
+    }
+  }
+}
+
+struct IndexEntry {
+  specifier: String,
+  kind: ModuleKind,
+  /// For `Js`/`Json`/`Wasm`: the module's byte offset into the data section.
+  /// For `Redirect`: the index of the entry this one resolves to.
+  source_offset: u32,
+  source_len: u32,
+  sourcemap_offset: u32,
+  sourcemap_len: u32,
+}
+
+/// A single self-contained archive of a build's module graph, modeled on
+/// Deno's eszip v2 layout: a length-prefixed index section followed by a
+/// data section of sha256-prefixed module blobs. Reuses the same
+/// little-endian length-prefix discipline as `Packet`.
+pub struct EszipArchive;
+
+impl EszipArchive {
+  /// Packs a completed build's output files into one archive.
+  pub fn from_build(result: &BuildResult) -> Vec<u8> {
+    let mut entries = Vec::new();
+    let mut data = Vec::new();
+    let mut blobs_by_hash: HashMap<[u8; HASH_LEN], usize> = HashMap::new();
+
+    let (sourcemaps, modules): (Vec<_>, Vec<_>) = result
+      .output_files
+      .iter()
+      .partition(|file| file.path.ends_with(".map"));
+
+    for module in modules {
+      let hash = sha256(&module.contents);
+
+      if let Some(&existing) = blobs_by_hash.get(&hash) {
+        entries.push(IndexEntry {
+          specifier: module.path.clone(),
+          kind: ModuleKind::Redirect,
+          source_offset: existing as u32,
+          source_len: 0,
+          sourcemap_offset: 0,
+          sourcemap_len: 0,
+        });
+        continue;
+      }
+
+      let source_offset = data.len() as u32;
+      data.extend_from_slice(&hash);
+      data.extend_from_slice(&module.contents);
+      let source_len = module.contents.len() as u32;
+
+      let sourcemap = sourcemaps
+        .iter()
+        .find(|map| map.path == format!("{}.map", module.path));
+      let (sourcemap_offset, sourcemap_len) = match sourcemap {
+        Some(sourcemap) => {
+          let offset = data.len() as u32;
+          data.extend_from_slice(&sha256(&sourcemap.contents));
+          data.extend_from_slice(&sourcemap.contents);
+          (offset, sourcemap.contents.len() as u32)
+        }
+        None => (0, 0),
+      };
+
+      blobs_by_hash.insert(hash, entries.len());
+      entries.push(IndexEntry {
+        specifier: module.path.clone(),
+        kind: ModuleKind::from_path(&module.path),
+        source_offset,
+        source_len,
+        sourcemap_offset,
+        sourcemap_len,
+      });
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    write_u32(&mut bytes, entries.len() as u32);
+    for entry in &entries {
+      write_u32(&mut bytes, entry.specifier.len() as u32);
+      bytes.extend(entry.specifier.as_bytes());
+      bytes.push(entry.kind.tag());
+      write_u32(&mut bytes, entry.source_offset);
+      write_u32(&mut bytes, entry.source_len);
+      write_u32(&mut bytes, entry.sourcemap_offset);
+      write_u32(&mut bytes, entry.sourcemap_len);
+    }
+    bytes.extend(data);
+    bytes
+  }
+
+  /// Rebuilds a specifier -> source bytes map from an archive produced by
+  /// [`EszipArchive::from_build`]. Source maps are not returned; only the
+  /// module source is, since that's what a module loader needs to resolve
+  /// an import graph.
+  pub fn read(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>, EszipError> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+      return Err(EszipError::BadMagic);
+    }
+    let bytes = &bytes[MAGIC.len()..];
+
+    let (count, mut next) = read_u32(bytes)?;
+    // Every index entry is at least a few bytes, so a count bigger than
+    // the remaining buffer can only be a corrupted or truncated archive.
+    if count as usize > next.len() {
+      return Err(EszipError::LengthOverflow);
+    }
+    struct Parsed {
+      specifier: String,
+      kind: ModuleKind,
+      source_offset: u32,
+      source_len: u32,
+    }
+    let mut parsed = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+      let (specifier_len, rest) = read_u32(next)?;
+      let (specifier, rest) = split_checked(rest, specifier_len as usize)?;
+      let specifier = std::str::from_utf8(specifier)
+        .map_err(|_| EszipError::InvalidUtf8)?
+        .to_string();
+      let (&tag, rest) = rest.split_first().ok_or(EszipError::UnexpectedEof)?;
+      let kind = ModuleKind::from_tag(tag)?;
+      let (source_offset, rest) = read_u32(rest)?;
+      let (source_len, rest) = read_u32(rest)?;
+      let (_sourcemap_offset, rest) = read_u32(rest)?;
+      let (_sourcemap_len, rest) = read_u32(rest)?;
+      next = rest;
+      parsed.push(Parsed {
+        specifier,
+        kind,
+        source_offset,
+        source_len,
+      });
+    }
+
+    let data = next;
+    let mut out = HashMap::new();
+    for entry in &parsed {
+      let source = match entry.kind {
+        ModuleKind::Redirect => {
+          let target = parsed
+            .get(entry.source_offset as usize)
+            .ok_or(EszipError::InvalidRedirect)?;
+          read_blob(data, target.source_offset, target.source_len)?
+        }
+        ModuleKind::Js | ModuleKind::Json | ModuleKind::Wasm => {
+          read_blob(data, entry.source_offset, entry.source_len)?
+        }
+      };
+      out.insert(entry.specifier.clone(), source);
+    }
+    Ok(out)
+  }
+}
+
+fn read_blob(data: &[u8], offset: u32, len: u32) -> Result<Vec<u8>, EszipError> {
+  let offset = offset as usize;
+  let len = len as usize;
+  let end = offset
+    .checked_add(HASH_LEN)
+    .and_then(|start| start.checked_add(len))
+    .ok_or(EszipError::UnexpectedEof)?;
+  if end > data.len() {
+    return Err(EszipError::UnexpectedEof);
+  }
+  let hash = &data[offset..offset + HASH_LEN];
+  let source = &data[offset + HASH_LEN..end];
+  if hash != sha256(source) {
+    return Err(EszipError::HashMismatch);
+  }
+  Ok(source.to_vec())
+}
+
+fn sha256(bytes: &[u8]) -> [u8; HASH_LEN] {
+  Sha256::digest(bytes).into()
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+  bytes.extend([0; 4]);
+  let len = bytes.len();
+  LE::write_u32(&mut bytes[len - 4..], value);
+}
+
+fn read_u32(bytes: &[u8]) -> Result<(u32, &[u8]), EszipError> {
+  if bytes.len() < 4 {
+    return Err(EszipError::UnexpectedEof);
+  }
+  let (head, tail) = bytes.split_at(4);
+  Ok((LE::read_u32(head), tail))
+}
+
+fn split_checked(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), EszipError> {
+  if len > bytes.len() {
+    return Err(EszipError::UnexpectedEof);
+  }
+  Ok(bytes.split_at(len))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EszipError {
+  BadMagic,
+  UnexpectedEof,
+  InvalidUtf8,
+  InvalidModuleKind(u8),
+  InvalidRedirect,
+  HashMismatch,
+  LengthOverflow,
+}
+
+impl fmt::Display for EszipError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::BadMagic => write!(f, "not an eszip archive"),
+      Self::UnexpectedEof => write!(f, "unexpected end of archive"),
+      Self::InvalidUtf8 => write!(f, "invalid utf-8 in archive"),
+      Self::InvalidModuleKind(tag) => write!(f, "invalid module kind: {tag}"),
+      Self::InvalidRedirect => write!(f, "redirect entry points outside the archive"),
+      Self::HashMismatch => write!(f, "module data failed its sha256 integrity check"),
+      Self::LengthOverflow => write!(f, "index entry count exceeds remaining buffer"),
+    }
+  }
+}
+
+impl std::error::Error for EszipError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::types::OutputFile;
+
+  fn build_result(files: Vec<(&str, &[u8])>) -> BuildResult {
+    BuildResult {
+      output_files: files
+        .into_iter()
+        .map(|(path, contents)| OutputFile {
+          path: path.to_string(),
+          contents: contents.to_vec(),
+        })
+        .collect(),
+      metafile: None,
+      warnings: Vec::new(),
+      errors: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let result = build_result(vec![
+      ("out/a.js", b"console.log('a')"),
+      ("out/a.js.map", b"{\"version\":3}"),
+      ("out/b.json", b"{\"ok\":true}"),
+    ]);
+
+    let archive = EszipArchive::from_build(&result);
+    let modules = EszipArchive::read(&archive).unwrap();
+
+    assert_eq!(modules.len(), 2);
+    assert_eq!(modules["out/a.js"], b"console.log('a')");
+    assert_eq!(modules["out/b.json"], b"{\"ok\":true}");
+  }
+
+  #[test]
+  fn test_deduplicates_identical_blobs() {
+    let result = build_result(vec![("out/a.js", b"shared"), ("out/b.js", b"shared")]);
+
+    let archive = EszipArchive::from_build(&result);
+    let modules = EszipArchive::read(&archive).unwrap();
+
+    assert_eq!(modules["out/a.js"], b"shared");
+    assert_eq!(modules["out/b.js"], b"shared");
+  }
+
+  #[test]
+  fn test_rejects_corrupted_archive() {
+    let result = build_result(vec![("out/a.js", b"console.log('a')")]);
+    let mut archive = EszipArchive::from_build(&result);
+    let last = archive.len() - 1;
+    archive[last] ^= 0xff;
+    assert_eq!(EszipArchive::read(&archive), Err(EszipError::HashMismatch));
+  }
+
+  #[test]
+  fn test_rejects_oversized_entry_count_without_allocating() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    write_u32(&mut bytes, u32::MAX); // bogus index entry count
+    assert_eq!(EszipArchive::read(&bytes), Err(EszipError::LengthOverflow));
+  }
+}
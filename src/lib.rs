@@ -0,0 +1,17 @@
+mod eszip;
+mod plugin;
+mod serve;
+mod service;
+mod stdio_protocol;
+mod types;
+
+pub use eszip::{EszipArchive, EszipError};
+pub use plugin::{OnLoadArgs, OnLoadResult, OnResolveArgs, OnResolveResult, Plugin};
+pub use serve::{ServeHandle, ServeOptions};
+pub use service::Service;
+pub use stdio_protocol::{Packet, ProtocolError, Value};
+pub use types::{
+  BuildOptions, BuildResult, Charset, CommonOptions, Drop, Engine, EngineName, Format, JSXMode,
+  LegalComments, Loader, LogLevel, Message, OutputFile, Platform, Sourcemap, Target,
+  TransformOptions, TransformResult,
+};
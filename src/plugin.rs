@@ -0,0 +1,90 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::types::{Loader, Message};
+
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+type OnResolveCallback =
+  Arc<dyn Fn(OnResolveArgs) -> BoxFuture<'static, OnResolveResult> + Send + Sync>;
+type OnLoadCallback = Arc<dyn Fn(OnLoadArgs) -> BoxFuture<'static, OnLoadResult> + Send + Sync>;
+
+/// The path esbuild is asking a plugin to resolve.
+/// https://esbuild.github.io/plugins/#on-resolve
+pub struct OnResolveArgs {
+  pub path: String,
+  pub importer: String,
+  pub namespace: String,
+  pub resolve_dir: String,
+  pub kind: String,
+}
+
+#[derive(Default)]
+pub struct OnResolveResult {
+  pub path: Option<String>,
+  pub namespace: Option<String>,
+  pub external: Option<bool>,
+  pub errors: Vec<Message>,
+  pub warnings: Vec<Message>,
+}
+
+/// The path esbuild is asking a plugin to load the contents of.
+/// https://esbuild.github.io/plugins/#on-load
+pub struct OnLoadArgs {
+  pub path: String,
+  pub namespace: String,
+}
+
+#[derive(Default)]
+pub struct OnLoadResult {
+  pub contents: Option<String>,
+  pub loader: Option<Loader>,
+  pub resolve_dir: Option<String>,
+  pub errors: Vec<Message>,
+  pub warnings: Vec<Message>,
+}
+
+/// A build plugin, registered by name with `on_resolve`/`on_load` hooks that
+/// are invoked when esbuild sends a matching server-initiated request back
+/// to the host during a build. https://esbuild.github.io/plugins/
+#[derive(Clone)]
+pub struct Plugin {
+  pub(crate) name: String,
+  pub(crate) on_resolve: Vec<(Regex, OnResolveCallback)>,
+  pub(crate) on_load: Vec<(Regex, OnLoadCallback)>,
+}
+
+impl Plugin {
+  pub fn new(name: impl Into<String>) -> Self {
+    Self {
+      name: name.into(),
+      on_resolve: Vec::new(),
+      on_load: Vec::new(),
+    }
+  }
+
+  pub fn on_resolve<F, Fut>(mut self, filter: Regex, callback: F) -> Self
+  where
+    F: Fn(OnResolveArgs) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = OnResolveResult> + Send + 'static,
+  {
+    self
+      .on_resolve
+      .push((filter, Arc::new(move |args| Box::pin(callback(args)))));
+    self
+  }
+
+  pub fn on_load<F, Fut>(mut self, filter: Regex, callback: F) -> Self
+  where
+    F: Fn(OnLoadArgs) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = OnLoadResult> + Send + 'static,
+  {
+    self
+      .on_load
+      .push((filter, Arc::new(move |args| Box::pin(callback(args)))));
+    self
+  }
+}
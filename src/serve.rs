@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::service::Service;
+use crate::stdio_protocol::Value;
+use crate::types::BuildOptions;
+
+/// https://esbuild.github.io/api/#serve-arguments
+pub struct ServeOptions {
+  pub host: String,
+  pub port: u16,
+  pub servedir: Option<String>,
+}
+
+/// A running dev server: the public-facing HTTP listener, backed by
+/// esbuild's own serve-mode server.
+pub struct ServeHandle {
+  pub host: String,
+  pub port: u16,
+}
+
+impl Service {
+  /// Starts esbuild's serve mode and exposes it on `serve_options`'
+  /// host/port. esbuild's `serve` command makes the esbuild child itself
+  /// open and own an HTTP listener that always serves the latest
+  /// successful build, so it's pointed at an ephemeral loopback address
+  /// that's never exposed; this process binds the actual public listener
+  /// and proxies every connection straight through to esbuild's.
+  /// https://esbuild.github.io/api/#serve-api
+  pub async fn serve(self: Arc<Self>, serve_options: ServeOptions, build_options: BuildOptions) -> ServeHandle {
+    let mut request = match Value::from(&build_options) {
+      Value::Map(map) => map,
+      _ => unreachable!("BuildOptions always converts to a Value::Map"),
+    };
+    request.insert("command".to_string(), Value::String("serve".to_string()));
+    request.insert("host".to_string(), Value::String("127.0.0.1".to_string()));
+    request.insert("port".to_string(), Value::Number(0));
+    if let Some(servedir) = &serve_options.servedir {
+      request.insert("servedir".to_string(), Value::String(servedir.clone()));
+    }
+
+    let ack = self.send_request(Value::Map(request)).await;
+    let upstream = match ack {
+      Value::Map(mut map) => {
+        let host = match map.remove("host") {
+          Some(Value::String(host)) => host,
+          _ => "127.0.0.1".to_string(),
+        };
+        let port = match map.remove("port") {
+          Some(Value::Number(port)) => port as u16,
+          _ => 0,
+        };
+        (host, port)
+      }
+      _ => ("127.0.0.1".to_string(), 0),
+    };
+
+    let listener = TcpListener::bind((serve_options.host.as_str(), serve_options.port))
+      .await
+      .expect("failed to bind serve listener");
+    let local_addr = listener.local_addr().expect("listener has no local address");
+
+    tokio::spawn(Self::accept_loop(listener, upstream));
+
+    ServeHandle {
+      host: local_addr.ip().to_string(),
+      port: local_addr.port(),
+    }
+  }
+
+  async fn accept_loop(listener: TcpListener, upstream: (String, u16)) {
+    loop {
+      let (downstream, _) = match listener.accept().await {
+        Ok(accepted) => accepted,
+        Err(_) => continue,
+      };
+      tokio::spawn(Self::proxy_connection(downstream, upstream.clone()));
+    }
+  }
+
+  async fn proxy_connection(mut downstream: TcpStream, upstream: (String, u16)) {
+    let Ok(mut upstream) = TcpStream::connect((upstream.0.as_str(), upstream.1)).await else {
+      return;
+    };
+    let _ = copy_bidirectional(&mut downstream, &mut upstream).await;
+  }
+}
@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex};
+
+use crate::plugin::{OnLoadArgs, OnResolveArgs, Plugin};
+use crate::stdio_protocol::{Packet, Value};
+use crate::types::{BuildOptions, BuildResult, Message, TransformOptions, TransformResult};
+
+/// The esbuild version this host speaks the `--service=` protocol for.
+const ESBUILD_VERSION: &str = "0.19.2";
+
+type Pending = Arc<Mutex<HashMap<u32, oneshot::Sender<Value>>>>;
+type Plugins = Arc<Mutex<HashMap<String, Arc<Plugin>>>>;
+type Stdin = Arc<Mutex<ChildStdin>>;
+
+/// A running `esbuild --service=...` child process, talking the
+/// length-prefixed `Packet` wire format over its stdin/stdout.
+pub struct Service {
+  child: Child,
+  stdin: Stdin,
+  pending: Pending,
+  plugins: Plugins,
+  next_id: AtomicU32,
+  /// Set once `read_loop` exits, meaning esbuild's stdout closed or sent
+  /// something undecodable. Checked by `send_request` so new calls fail
+  /// fast instead of parking on a oneshot no reader will ever resolve.
+  dead: Arc<AtomicBool>,
+}
+
+impl Service {
+  /// Spawns the esbuild binary in service mode and starts the background
+  /// task that reads response packets off its stdout.
+  pub async fn start() -> Self {
+    let mut child = Command::new("esbuild")
+      .arg(format!("--service={ESBUILD_VERSION}"))
+      .arg("--ping")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::inherit())
+      .kill_on_drop(true)
+      .spawn()
+      .expect("failed to spawn esbuild");
+
+    let stdin: Stdin = Arc::new(Mutex::new(
+      child.stdin.take().expect("child stdin was not piped"),
+    ));
+    let stdout = child.stdout.take().expect("child stdout was not piped");
+    let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let plugins: Plugins = Arc::new(Mutex::new(HashMap::new()));
+    let dead = Arc::new(AtomicBool::new(false));
+
+    tokio::spawn(Self::read_loop(
+      stdout,
+      pending.clone(),
+      plugins.clone(),
+      stdin.clone(),
+      dead.clone(),
+    ));
+
+    Self {
+      child,
+      stdin,
+      pending,
+      plugins,
+      next_id: AtomicU32::new(0),
+      dead,
+    }
+  }
+
+  /// Sends `value` to esbuild as a new request and waits for its response.
+  pub async fn send_request(&self, value: Value) -> Value {
+    let id = self.next_id.fetch_add(2, Ordering::SeqCst);
+    let (tx, rx) = oneshot::channel();
+    {
+      // Checked under the `pending` lock: `read_loop` sets `dead` before
+      // it clears `pending`, so a sender inserted here is either cleared
+      // by it or never inserted at all.
+      let mut pending = self.pending.lock().await;
+      if self.dead.load(Ordering::SeqCst) {
+        panic!("esbuild service is no longer running");
+      }
+      pending.insert(id, tx);
+    }
+
+    Self::write_packet(&self.stdin, Packet::new(id, true, value)).await;
+
+    rx.await.expect("esbuild closed the connection")
+  }
+
+  /// Transforms a single file of source code, e.g. stripping TypeScript
+  /// types or minifying. https://esbuild.github.io/api/#transform-api
+  pub async fn transform(
+    &self,
+    code: impl Into<String>,
+    options: &TransformOptions,
+  ) -> TransformResult {
+    let mut request = match Value::from(options) {
+      Value::Map(map) => map,
+      _ => unreachable!("TransformOptions always converts to a Value::Map"),
+    };
+    request.insert("command".to_string(), Value::String("transform".to_string()));
+    request.insert("input".to_string(), Value::String(code.into()));
+
+    let response = self.send_request(Value::Map(request)).await;
+    TransformResult::from(response)
+  }
+
+  /// Bundles one or more entry points, calling back into any registered
+  /// plugins' `on_resolve`/`on_load` hooks as esbuild asks for them.
+  /// https://esbuild.github.io/api/#build-api
+  pub async fn build(&self, options: BuildOptions) -> BuildResult {
+    let mut request = match Value::from(&options) {
+      Value::Map(map) => map,
+      _ => unreachable!("BuildOptions always converts to a Value::Map"),
+    };
+    request.insert("command".to_string(), Value::String("build".to_string()));
+
+    let plugins: Vec<Arc<Plugin>> = options.plugins.into_iter().map(Arc::new).collect();
+    {
+      let mut registry = self.plugins.lock().await;
+      for plugin in &plugins {
+        registry.insert(plugin.name.clone(), plugin.clone());
+      }
+    }
+
+    let response = self.send_request(Value::Map(request)).await;
+
+    {
+      let mut registry = self.plugins.lock().await;
+      for plugin in &plugins {
+        registry.remove(&plugin.name);
+      }
+    }
+
+    BuildResult::from(response)
+  }
+
+  async fn write_packet(stdin: &Stdin, packet: Packet) {
+    stdin
+      .lock()
+      .await
+      .write_all(&packet.encode())
+      .await
+      .expect("failed to write to esbuild stdin");
+  }
+
+  async fn read_loop(
+    mut stdout: tokio::process::ChildStdout,
+    pending: Pending,
+    plugins: Plugins,
+    stdin: Stdin,
+    dead: Arc<AtomicBool>,
+  ) {
+    loop {
+      let mut len_bytes = [0u8; 4];
+      if stdout.read_exact(&mut len_bytes).await.is_err() {
+        break;
+      }
+      let len = u32::from_le_bytes(len_bytes) as usize;
+
+      let mut body = vec![0u8; len];
+      if stdout.read_exact(&mut body).await.is_err() {
+        break;
+      }
+
+      let packet = match Packet::decode(&body) {
+        Ok(packet) => packet,
+        Err(err) => {
+          eprintln!("esbuild protocol error: {err}");
+          break;
+        }
+      };
+      if packet.is_request() {
+        // Server-initiated requests: esbuild is asking a plugin's
+        // `on-resolve`/`on-load` hook to handle a path during a build.
+        let id = packet.id();
+        let plugins = plugins.clone();
+        let stdin = stdin.clone();
+        tokio::spawn(async move {
+          let response = Self::dispatch_plugin_request(packet.into_value(), &plugins).await;
+          Self::write_packet(&stdin, Packet::new(id, false, response)).await;
+        });
+        continue;
+      }
+
+      let id = packet.id();
+      if let Some(tx) = pending.lock().await.remove(&id) {
+        let _ = tx.send(packet.into_value());
+      }
+    }
+
+    // esbuild's stdout closed or sent something undecodable: no reader
+    // will ever resolve another response. Mark the service dead so new
+    // `send_request` calls fail fast, and drop every outstanding sender
+    // so in-flight calls already parked on `rx.await` fail immediately
+    // instead of hanging forever.
+    dead.store(true, Ordering::SeqCst);
+    pending.lock().await.clear();
+  }
+
+  async fn dispatch_plugin_request(request: Value, plugins: &Plugins) -> Value {
+    let Value::Map(mut request) = request else {
+      return Value::Map(BTreeMap::new());
+    };
+
+    let command = match request.remove("command") {
+      Some(Value::String(command)) => command,
+      _ => return Value::Map(BTreeMap::new()),
+    };
+    let plugin_name = match request.remove("pluginName") {
+      Some(Value::String(name)) => name,
+      _ => return Value::Map(BTreeMap::new()),
+    };
+    let path = match request.get("path") {
+      Some(Value::String(path)) => path.clone(),
+      _ => return Value::Map(BTreeMap::new()),
+    };
+
+    let plugin = match plugins.lock().await.get(&plugin_name).cloned() {
+      Some(plugin) => plugin,
+      None => return Value::Map(BTreeMap::new()),
+    };
+
+    match command.as_str() {
+      "resolve" => {
+        let mut string_field = |key: &str| match request.remove(key) {
+          Some(Value::String(value)) => value,
+          _ => String::new(),
+        };
+        let args = OnResolveArgs {
+          path: path.clone(),
+          importer: string_field("importer"),
+          namespace: string_field("namespace"),
+          resolve_dir: string_field("resolveDir"),
+          kind: string_field("kind"),
+        };
+
+        let hook = plugin
+          .on_resolve
+          .iter()
+          .find(|(filter, _)| filter.is_match(&path))
+          .map(|(_, callback)| callback.clone());
+
+        let Some(hook) = hook else {
+          return Value::Map(BTreeMap::new());
+        };
+        let result = hook(args).await;
+
+        let mut response = BTreeMap::new();
+        if let Some(path) = result.path {
+          response.insert("path".to_string(), Value::String(path));
+        }
+        if let Some(namespace) = result.namespace {
+          response.insert("namespace".to_string(), Value::String(namespace));
+        }
+        if let Some(external) = result.external {
+          response.insert("external".to_string(), Value::Boolean(external));
+        }
+        response.insert("errors".to_string(), messages_to_value(result.errors));
+        response.insert("warnings".to_string(), messages_to_value(result.warnings));
+        Value::Map(response)
+      }
+      "load" => {
+        let mut string_field = |key: &str| match request.remove(key) {
+          Some(Value::String(value)) => value,
+          _ => String::new(),
+        };
+        let args = OnLoadArgs {
+          path: path.clone(),
+          namespace: string_field("namespace"),
+        };
+
+        let hook = plugin
+          .on_load
+          .iter()
+          .find(|(filter, _)| filter.is_match(&path))
+          .map(|(_, callback)| callback.clone());
+
+        let Some(hook) = hook else {
+          return Value::Map(BTreeMap::new());
+        };
+        let result = hook(args).await;
+
+        let mut response = BTreeMap::new();
+        if let Some(contents) = result.contents {
+          response.insert("contents".to_string(), Value::String(contents));
+        }
+        if let Some(loader) = result.loader {
+          response.insert("loader".to_string(), Value::String(loader.as_str().to_string()));
+        }
+        if let Some(resolve_dir) = result.resolve_dir {
+          response.insert("resolveDir".to_string(), Value::String(resolve_dir));
+        }
+        response.insert("errors".to_string(), messages_to_value(result.errors));
+        response.insert("warnings".to_string(), messages_to_value(result.warnings));
+        Value::Map(response)
+      }
+      _ => Value::Map(BTreeMap::new()),
+    }
+  }
+}
+
+fn messages_to_value(messages: Vec<Message>) -> Value {
+  Value::Array(
+    messages
+      .into_iter()
+      .map(|message| {
+        let mut map = BTreeMap::new();
+        map.insert("text".to_string(), Value::String(message.text));
+        map.insert("location".to_string(), message.location.unwrap_or(Value::Null));
+        Value::Map(map)
+      })
+      .collect(),
+  )
+}
+
+impl Drop for Service {
+  fn drop(&mut self) {
+    let _ = self.child.start_kill();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use regex::Regex;
+
+  use super::*;
+  use crate::plugin::{OnLoadResult, OnResolveResult};
+  use crate::types::Loader;
+
+  fn plugins_with(plugin: Plugin) -> Plugins {
+    let mut registry = HashMap::new();
+    registry.insert(plugin.name.clone(), Arc::new(plugin));
+    Arc::new(Mutex::new(registry))
+  }
+
+  fn string_map(entries: &[(&str, &str)]) -> Value {
+    let mut map = BTreeMap::new();
+    for (key, value) in entries {
+      map.insert(key.to_string(), Value::String(value.to_string()));
+    }
+    Value::Map(map)
+  }
+
+  #[tokio::test]
+  async fn test_dispatch_plugin_request_routes_resolve_to_matching_hook() {
+    let plugin = Plugin::new("test-plugin").on_resolve(Regex::new(r"^virtual:").unwrap(), |args| async move {
+      OnResolveResult {
+        path: Some(format!("/resolved/{}", args.path)),
+        namespace: Some("virtual".to_string()),
+        ..Default::default()
+      }
+    });
+    let plugins = plugins_with(plugin);
+
+    let request = string_map(&[
+      ("command", "resolve"),
+      ("pluginName", "test-plugin"),
+      ("path", "virtual:thing"),
+      ("importer", ""),
+      ("namespace", ""),
+      ("resolveDir", ""),
+      ("kind", "import-statement"),
+    ]);
+
+    let Value::Map(response) = Service::dispatch_plugin_request(request, &plugins).await else {
+      panic!("expected a Value::Map response");
+    };
+    assert_eq!(
+      response.get("path"),
+      Some(&Value::String("/resolved/virtual:thing".to_string()))
+    );
+    assert_eq!(
+      response.get("namespace"),
+      Some(&Value::String("virtual".to_string()))
+    );
+  }
+
+  #[tokio::test]
+  async fn test_dispatch_plugin_request_falls_through_when_no_hook_matches() {
+    let plugin = Plugin::new("test-plugin")
+      .on_resolve(Regex::new(r"^virtual:").unwrap(), |_| async move { OnResolveResult::default() });
+    let plugins = plugins_with(plugin);
+
+    let request = string_map(&[
+      ("command", "resolve"),
+      ("pluginName", "test-plugin"),
+      ("path", "not-virtual"),
+    ]);
+
+    let response = Service::dispatch_plugin_request(request, &plugins).await;
+    assert_eq!(response, Value::Map(BTreeMap::new()));
+  }
+
+  #[tokio::test]
+  async fn test_dispatch_plugin_request_routes_load_to_matching_hook() {
+    let plugin = Plugin::new("test-plugin").on_load(Regex::new(r"\.virtual$").unwrap(), |_| async move {
+      OnLoadResult {
+        contents: Some("export default 1;".to_string()),
+        loader: Some(Loader::JS),
+        ..Default::default()
+      }
+    });
+    let plugins = plugins_with(plugin);
+
+    let request = string_map(&[
+      ("command", "load"),
+      ("pluginName", "test-plugin"),
+      ("path", "thing.virtual"),
+      ("namespace", "virtual"),
+    ]);
+
+    let Value::Map(response) = Service::dispatch_plugin_request(request, &plugins).await else {
+      panic!("expected a Value::Map response");
+    };
+    assert_eq!(
+      response.get("contents"),
+      Some(&Value::String("export default 1;".to_string()))
+    );
+    assert_eq!(response.get("loader"), Some(&Value::String("js".to_string())));
+  }
+}
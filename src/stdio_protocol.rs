@@ -1,7 +1,38 @@
 use std::collections::BTreeMap;
+use std::fmt;
 
 use byteorder::{ByteOrder, LE};
 
+/// Why a `Packet` could not be decoded from a byte buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+  /// The buffer ended before a tag byte, boolean byte or `u32` (including
+  /// a length prefix itself) could be read.
+  UnexpectedEof,
+  /// A `Value` tag byte didn't match any known variant.
+  InvalidTag(u8),
+  /// A string field's bytes were not valid UTF-8.
+  InvalidUtf8,
+  /// Bytes remained after decoding the packet's top-level value.
+  TrailingBytes,
+  /// A length prefix claimed more elements/bytes than the buffer could hold.
+  LengthOverflow,
+}
+
+impl fmt::Display for ProtocolError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::UnexpectedEof => write!(f, "unexpected end of packet"),
+      Self::InvalidTag(tag) => write!(f, "invalid value tag: {tag}"),
+      Self::InvalidUtf8 => write!(f, "invalid utf-8 in packet"),
+      Self::TrailingBytes => write!(f, "trailing bytes after packet"),
+      Self::LengthOverflow => write!(f, "length prefix exceeds remaining buffer"),
+    }
+  }
+}
+
+impl std::error::Error for ProtocolError {}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Value {
   Null,
@@ -15,12 +46,32 @@ pub enum Value {
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Packet {
-  id: u32,
-  is_request: bool,
-  value: Value,
+  pub(crate) id: u32,
+  pub(crate) is_request: bool,
+  pub(crate) value: Value,
 }
 
 impl Packet {
+  pub fn new(id: u32, is_request: bool, value: Value) -> Self {
+    Self {
+      id,
+      is_request,
+      value,
+    }
+  }
+
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  pub fn is_request(&self) -> bool {
+    self.is_request
+  }
+
+  pub fn into_value(self) -> Value {
+    self.value
+  }
+
   pub fn encode(self) -> Vec<u8> {
     let mut bytes = Vec::new();
     fn visit(value: Value, bytes: &mut Vec<u8>) {
@@ -77,70 +128,84 @@ impl Packet {
     bytes
   }
 
-  pub fn decode(bytes: Vec<u8>) -> Self {
-    fn visit(bytes: &[u8]) -> (Value, &[u8]) {
-      let kind = bytes[0];
-      let bytes = &bytes[1..];
+  /// Decodes a packet's body (the bytes after the outer length prefix).
+  /// Bounds-checked throughout: malformed input yields a `ProtocolError`
+  /// instead of panicking, since these bytes come straight off a child
+  /// process that could crash or emit a partial frame. Takes `&[u8]`
+  /// rather than an owned `Vec<u8>` so intermediate slicing is
+  /// allocation-free, but `Value::String`/`Value::Uint8Array` still own
+  /// their payload, so the final string/byte fields are still copied out
+  /// of `bytes`; true zero-copy decoding would need a lifetime-carrying
+  /// `Value<'a>` borrowing from the input buffer, which is out of scope
+  /// here.
+  pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+    fn visit(bytes: &[u8]) -> Result<(Value, &[u8]), ProtocolError> {
+      let (&kind, bytes) = bytes.split_first().ok_or(ProtocolError::UnexpectedEof)?;
       match kind {
-        0 => (Value::Null, bytes),
+        0 => Ok((Value::Null, bytes)),
         1 => {
-          let value = bytes[0];
-          let next = &bytes[1..];
-          (Value::Boolean(value != 0), next)
+          let (&value, next) = bytes.split_first().ok_or(ProtocolError::UnexpectedEof)?;
+          Ok((Value::Boolean(value != 0), next))
         }
         2 => {
-          let (value, next) = read_u32(bytes);
-          (Value::Number(value as i32), next)
+          let (value, next) = read_u32(bytes)?;
+          Ok((Value::Number(value as i32), next))
         }
         3 => {
-          let (value, next) = read_length_prefixed(bytes);
-          (
-            Value::String(String::from_utf8(value.to_vec()).unwrap()),
-            next,
-          )
+          let (value, next) = read_length_prefixed(bytes)?;
+          let value = std::str::from_utf8(value).map_err(|_| ProtocolError::InvalidUtf8)?;
+          Ok((Value::String(value.to_string()), next))
         }
         4 => {
-          let (value, next) = read_length_prefixed(bytes);
-          (Value::Uint8Array(value.to_vec()), next)
+          let (value, next) = read_length_prefixed(bytes)?;
+          Ok((Value::Uint8Array(value.to_vec()), next))
         }
         5 => {
-          let (len, mut next) = read_u32(bytes);
+          let (len, mut next) = read_u32(bytes)?;
+          // Every element is at least one tag byte, so a length prefix
+          // bigger than the remaining buffer can only be malformed input.
+          if len as usize > next.len() {
+            return Err(ProtocolError::LengthOverflow);
+          }
           let mut value = Vec::with_capacity(len as usize);
           for _ in 0..len {
-            let (v, n) = visit(next);
+            let (v, n) = visit(next)?;
             next = n;
             value.push(v);
           }
-          (Value::Array(value), next)
+          Ok((Value::Array(value), next))
         }
         6 => {
-          let (len, mut next) = read_u32(bytes);
+          let (len, mut next) = read_u32(bytes)?;
+          if len as usize > next.len() {
+            return Err(ProtocolError::LengthOverflow);
+          }
           let mut value = BTreeMap::new();
           for _ in 0..len {
-            let (key, n) = read_length_prefixed(next);
-            next = n;
-            let k = String::from_utf8(key.to_vec()).unwrap();
-            let (v, n) = visit(next);
+            let (key, n) = read_length_prefixed(next)?;
+            let key = std::str::from_utf8(key).map_err(|_| ProtocolError::InvalidUtf8)?;
+            let (v, n) = visit(n)?;
             next = n;
-            value.insert(k, v);
+            value.insert(key.to_string(), v);
           }
-          (Value::Map(value), next)
+          Ok((Value::Map(value), next))
         }
-        _ => panic!("Invalid packet"),
+        kind => Err(ProtocolError::InvalidTag(kind)),
       }
     }
-    let (mut id, next) = read_u32(&bytes);
+
+    let (mut id, next) = read_u32(bytes)?;
     let is_request = (id & 1) == 0;
     id >>= 1;
-    let (value, next) = visit(next);
+    let (value, next) = visit(next)?;
     if !next.is_empty() {
-      panic!("Invalid packet");
+      return Err(ProtocolError::TrailingBytes);
     }
-    Self {
+    Ok(Self {
       id,
       is_request,
       value,
-    }
+    })
   }
 }
 
@@ -150,13 +215,21 @@ fn write_u32(bytes: &mut Vec<u8>, value: u32) {
   LE::write_u32(&mut bytes[len - 4..], value);
 }
 
-fn read_u32(bytes: &[u8]) -> (u32, &[u8]) {
-  (LE::read_u32(bytes), &bytes[4..])
+fn read_u32(bytes: &[u8]) -> Result<(u32, &[u8]), ProtocolError> {
+  if bytes.len() < 4 {
+    return Err(ProtocolError::UnexpectedEof);
+  }
+  let (head, tail) = bytes.split_at(4);
+  Ok((LE::read_u32(head), tail))
 }
 
-fn read_length_prefixed(bytes: &[u8]) -> (&[u8], &[u8]) {
-  let (len, next) = read_u32(bytes);
-  (&next[..len as usize], &next[len as usize..])
+fn read_length_prefixed(bytes: &[u8]) -> Result<(&[u8], &[u8]), ProtocolError> {
+  let (len, next) = read_u32(bytes)?;
+  let len = len as usize;
+  if len > next.len() {
+    return Err(ProtocolError::LengthOverflow);
+  }
+  Ok(next.split_at(len))
 }
 
 #[cfg(test)]
@@ -196,8 +269,65 @@ mod tests {
       value,
     };
     let buf = p1.clone().encode();
-    let (buf, _) = read_length_prefixed(&buf);
-    let p2 = Packet::decode(buf.to_vec());
+    let (buf, _) = read_length_prefixed(&buf).unwrap();
+    let p2 = Packet::decode(buf).unwrap();
     assert_eq!(p1, p2);
   }
+
+  #[test]
+  fn test_decode_truncated_input_does_not_panic() {
+    let p1 = Packet {
+      id: 1,
+      is_request: true,
+      value: Value::String("hello world".to_string()),
+    };
+    let buf = p1.encode();
+    let (body, _) = read_length_prefixed(&buf).unwrap();
+
+    for len in 0..body.len() {
+      assert!(Packet::decode(&body[..len]).is_err());
+    }
+  }
+
+  #[test]
+  fn test_decode_oversized_length_prefix_does_not_panic() {
+    // A string claiming to be far longer than the remaining buffer.
+    let mut body = Vec::new();
+    write_u32(&mut body, 0); // id, is_request
+    body.push(3); // Value::String tag
+    write_u32(&mut body, u32::MAX); // bogus length prefix
+    body.extend(b"short");
+    assert_eq!(Packet::decode(&body), Err(ProtocolError::LengthOverflow));
+
+    // An array claiming far more elements than the buffer has bytes for.
+    let mut body = Vec::new();
+    write_u32(&mut body, 0);
+    body.push(5); // Value::Array tag
+    write_u32(&mut body, u32::MAX);
+    assert_eq!(Packet::decode(&body), Err(ProtocolError::LengthOverflow));
+  }
+
+  #[test]
+  fn test_decode_invalid_tag_and_trailing_bytes() {
+    let mut body = Vec::new();
+    write_u32(&mut body, 0);
+    body.push(42); // not a valid Value tag
+    assert_eq!(Packet::decode(&body), Err(ProtocolError::InvalidTag(42)));
+
+    let mut body = Vec::new();
+    write_u32(&mut body, 0);
+    body.push(0); // Value::Null
+    body.push(0); // trailing garbage
+    assert_eq!(Packet::decode(&body), Err(ProtocolError::TrailingBytes));
+  }
+
+  #[test]
+  fn test_decode_invalid_utf8() {
+    let mut body = Vec::new();
+    write_u32(&mut body, 0);
+    body.push(3); // Value::String tag
+    write_u32(&mut body, 1);
+    body.push(0xff); // not valid UTF-8
+    assert_eq!(Packet::decode(&body), Err(ProtocolError::InvalidUtf8));
+  }
 }
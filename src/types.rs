@@ -1,19 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use regex::Regex;
 
+use crate::plugin::Plugin;
+use crate::stdio_protocol::Value;
+
+#[derive(Clone)]
 pub enum Platform {
   Browser,
   Node,
   Neutral,
 }
 
+impl Platform {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Browser => "browser",
+      Self::Node => "node",
+      Self::Neutral => "neutral",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Format {
   IIFE,
   CommonJS,
   ESModule,
 }
 
+impl Format {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::IIFE => "iife",
+      Self::CommonJS => "cjs",
+      Self::ESModule => "esm",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Loader {
   JS,
   JSX,
@@ -29,6 +55,26 @@ pub enum Loader {
   Default,
 }
 
+impl Loader {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::JS => "js",
+      Self::JSX => "jsx",
+      Self::TS => "ts",
+      Self::TSX => "tsx",
+      Self::CSS => "css",
+      Self::JSON => "json",
+      Self::Text => "text",
+      Self::Base64 => "base64",
+      Self::File => "file",
+      Self::Dataurl => "dataurl",
+      Self::Binary => "binary",
+      Self::Default => "default",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum LogLevel {
   Verbose,
   Debug,
@@ -38,16 +84,50 @@ pub enum LogLevel {
   Silent,
 }
 
+impl LogLevel {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Verbose => "verbose",
+      Self::Debug => "debug",
+      Self::Info => "info",
+      Self::Warning => "warning",
+      Self::Error => "error",
+      Self::Silent => "silent",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Charset {
   Ascii,
   Utf8,
 }
 
+impl Charset {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Ascii => "ascii",
+      Self::Utf8 => "utf8",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Drop {
   Console,
   Debugger,
 }
 
+impl Drop {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Console => "console",
+      Self::Debugger => "debugger",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Sourcemap {
   Inline,
   Linked,
@@ -55,6 +135,18 @@ pub enum Sourcemap {
   Both,
 }
 
+impl Sourcemap {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Inline => "inline",
+      Self::Linked => "linked",
+      Self::External => "external",
+      Self::Both => "both",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum LegalComments {
   None,
   Inline,
@@ -63,6 +155,19 @@ pub enum LegalComments {
   External,
 }
 
+impl LegalComments {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::None => "none",
+      Self::Inline => "inline",
+      Self::EndOfFile => "eof",
+      Self::Linked => "linked",
+      Self::External => "external",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum Target {
   ESNext,
   ES5,
@@ -76,6 +181,24 @@ pub enum Target {
   ES2022,
 }
 
+impl Target {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::ESNext => "esnext",
+      Self::ES5 => "es5",
+      Self::ES2015 => "es2015",
+      Self::ES2016 => "es2016",
+      Self::ES2017 => "es2017",
+      Self::ES2018 => "es2018",
+      Self::ES2019 => "es2019",
+      Self::ES2020 => "es2020",
+      Self::ES2021 => "es2021",
+      Self::ES2022 => "es2022",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum EngineName {
   EngineChrome,
   EngineEdge,
@@ -87,16 +210,52 @@ pub enum EngineName {
   EngineSafari,
 }
 
+impl EngineName {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::EngineChrome => "chrome",
+      Self::EngineEdge => "edge",
+      Self::EngineFirefo => "firefox",
+      Self::EngineIE => "ie",
+      Self::EngineIOS => "ios",
+      Self::EngineNode => "node",
+      Self::EngineOpera => "opera",
+      Self::EngineSafari => "safari",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub struct Engine {
   name: EngineName,
   version: String,
 }
 
+impl From<&Engine> for Value {
+  fn from(engine: &Engine) -> Self {
+    let mut map = BTreeMap::new();
+    map.insert("name".to_string(), Value::String(engine.name.as_str().to_string()));
+    map.insert("version".to_string(), Value::String(engine.version.clone()));
+    Value::Map(map)
+  }
+}
+
+#[derive(Clone)]
 pub enum JSXMode {
   Transform,
   Preserve,
 }
 
+impl JSXMode {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Transform => "transform",
+      Self::Preserve => "preserve",
+    }
+  }
+}
+
+#[derive(Clone)]
 pub struct CommonOptions {
   /// https://esbuild.github.io/api/#sourcemap
   sourcemap: Option<Sourcemap>,
@@ -115,6 +274,8 @@ pub struct CommonOptions {
   target: Option<Target>,
   /// https://esbuild.github.io/api/#target
   engines: Option<Vec<Engine>>,
+  /// https://esbuild.github.io/api/#platform
+  platform: Option<Platform>,
 
   /// https://esbuild.github.io/api/#mangle-props
   mangle_props: Option<Regex>,
@@ -158,12 +319,173 @@ pub struct CommonOptions {
   /// https://esbuild.github.io/api/#color
   color: Option<bool>,
   /// https://esbuild.github.io/api/#log-level
-  logLevel: Option<LogLevel>,
+  log_level: Option<LogLevel>,
   /// https://esbuild.github.io/api/#log-limit
-  logLimit: i32,
+  log_limit: i32,
 }
 
+fn common_options_into_map(options: &CommonOptions) -> BTreeMap<String, Value> {
+  let mut map = BTreeMap::new();
+
+  if let Some(sourcemap) = &options.sourcemap {
+    map.insert(
+      "sourcemap".to_string(),
+      Value::String(sourcemap.as_str().to_string()),
+    );
+  }
+  if let Some(legal_comments) = &options.legal_comments {
+    map.insert(
+      "legalComments".to_string(),
+      Value::String(legal_comments.as_str().to_string()),
+    );
+  }
+  if let Some(source_root) = &options.source_root {
+    map.insert("sourceRoot".to_string(), Value::String(source_root.clone()));
+  }
+  if let Some(sources_content) = options.sources_content {
+    map.insert("sourcesContent".to_string(), Value::Boolean(sources_content));
+  }
+
+  if let Some(format) = &options.format {
+    map.insert("format".to_string(), Value::String(format.as_str().to_string()));
+  }
+  if !options.global_name.is_empty() {
+    map.insert(
+      "globalName".to_string(),
+      Value::String(options.global_name.clone()),
+    );
+  }
+  if let Some(target) = &options.target {
+    map.insert("target".to_string(), Value::String(target.as_str().to_string()));
+  }
+  if let Some(engines) = &options.engines {
+    map.insert(
+      "engines".to_string(),
+      Value::Array(engines.iter().map(Value::from).collect()),
+    );
+  }
+  if let Some(platform) = &options.platform {
+    map.insert("platform".to_string(), Value::String(platform.as_str().to_string()));
+  }
+
+  if let Some(mangle_props) = &options.mangle_props {
+    map.insert(
+      "mangleProps".to_string(),
+      Value::String(mangle_props.as_str().to_string()),
+    );
+  }
+  if let Some(reserve_props) = &options.reserve_props {
+    map.insert(
+      "reserveProps".to_string(),
+      Value::String(reserve_props.as_str().to_string()),
+    );
+  }
+  if let Some(mangle_quoted) = options.mangle_quoted {
+    map.insert("mangleQuoted".to_string(), Value::Boolean(mangle_quoted));
+  }
+  if let Some(mangle_cache) = &options.mangle_cache {
+    map.insert(
+      "mangleCache".to_string(),
+      Value::Map(
+        mangle_cache
+          .iter()
+          .map(|(key, value)| {
+            let value = match value {
+              Some(value) => Value::String(value.clone()),
+              None => Value::Boolean(false),
+            };
+            (key.clone(), value)
+          })
+          .collect(),
+      ),
+    );
+  }
+  if let Some(drop) = &options.drop {
+    map.insert(
+      "drop".to_string(),
+      Value::Array(
+        drop
+          .iter()
+          .map(|d| Value::String(d.as_str().to_string()))
+          .collect(),
+      ),
+    );
+  }
+  map.insert("minify".to_string(), Value::Boolean(options.minify));
+  map.insert(
+    "minifyWhitespace".to_string(),
+    Value::Boolean(options.minify_whitespace),
+  );
+  map.insert(
+    "minifyIdentifiers".to_string(),
+    Value::Boolean(options.minify_identifiers),
+  );
+  map.insert(
+    "minifySyntax".to_string(),
+    Value::Boolean(options.minify_syntax),
+  );
+  if let Some(charset) = &options.charset {
+    map.insert("charset".to_string(), Value::String(charset.as_str().to_string()));
+  }
+  if let Some(tree_shaking) = options.tree_shaking {
+    map.insert("treeShaking".to_string(), Value::Boolean(tree_shaking));
+  }
+  map.insert(
+    "ignoreAnnotations".to_string(),
+    Value::Boolean(options.ignore_annotations),
+  );
+
+  if let Some(jsx) = &options.jsx {
+    map.insert("jsx".to_string(), Value::String(jsx.as_str().to_string()));
+  }
+  if let Some(jsx_factory) = &options.jsx_factory {
+    map.insert("jsxFactory".to_string(), Value::String(jsx_factory.clone()));
+  }
+  if let Some(jsx_fragment) = &options.jsx_fragment {
+    map.insert(
+      "jsxFragment".to_string(),
+      Value::String(jsx_fragment.clone()),
+    );
+  }
+
+  if let Some(define) = &options.define {
+    map.insert(
+      "define".to_string(),
+      Value::Map(
+        define
+          .iter()
+          .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+          .collect(),
+      ),
+    );
+  }
+  if let Some(pure) = &options.pure {
+    map.insert(
+      "pure".to_string(),
+      Value::Array(pure.iter().map(|p| Value::String(p.clone())).collect()),
+    );
+  }
+  map.insert("keepNames".to_string(), Value::Boolean(options.keep_names));
+
+  if let Some(color) = options.color {
+    map.insert("color".to_string(), Value::Boolean(color));
+  }
+  if let Some(log_level) = &options.log_level {
+    map.insert(
+      "logLevel".to_string(),
+      Value::String(log_level.as_str().to_string()),
+    );
+  }
+  map.insert("logLimit".to_string(), Value::Number(options.log_limit));
+
+  map
+}
+
+#[derive(Clone)]
 pub struct TransformOptions {
+  /// Options shared with the build API.
+  /// https://esbuild.github.io/api/#transform-api
+  pub common: CommonOptions,
   /// https://esbuild.github.io/api/#tsconfig-raw
   tsconfig_raw: String,
   /// Documentation: https://esbuild.github.io/api/#loader
@@ -175,3 +497,400 @@ pub struct TransformOptions {
   /// https://esbuild.github.io/api/#footer
   footer: String,
 }
+
+impl From<&TransformOptions> for Value {
+  fn from(options: &TransformOptions) -> Self {
+    let mut map = common_options_into_map(&options.common);
+
+    if !options.tsconfig_raw.is_empty() {
+      map.insert(
+        "tsconfigRaw".to_string(),
+        Value::String(options.tsconfig_raw.clone()),
+      );
+    }
+    if let Some(loader) = &options.loader {
+      map.insert("loader".to_string(), Value::String(loader.as_str().to_string()));
+    }
+    if !options.sourcefile.is_empty() {
+      map.insert("sourcefile".to_string(), Value::String(options.sourcefile.clone()));
+    }
+    if !options.banner.is_empty() {
+      map.insert("banner".to_string(), Value::String(options.banner.clone()));
+    }
+    if !options.footer.is_empty() {
+      map.insert("footer".to_string(), Value::String(options.footer.clone()));
+    }
+
+    Value::Map(map)
+  }
+}
+
+/// Unwraps the `Value::Map` backing a response, or `None` if esbuild sent
+/// some other shape, e.g. a version mismatch or a crash mid-reply. Every
+/// `From<Value>` response type below falls back to an empty/default
+/// value in that case rather than panicking, since these bytes come
+/// straight off a child process that could crash or emit a malformed
+/// reply.
+fn into_map(value: Value) -> Option<BTreeMap<String, Value>> {
+  match value {
+    Value::Map(map) => Some(map),
+    _ => None,
+  }
+}
+
+/// A diagnostic produced by esbuild, e.g. a parse warning or a bundling error.
+pub struct Message {
+  pub text: String,
+  pub location: Option<Value>,
+}
+
+impl From<Value> for Message {
+  fn from(value: Value) -> Self {
+    let Some(mut map) = into_map(value) else {
+      return Self {
+        text: String::new(),
+        location: None,
+      };
+    };
+    let text = match map.remove("text") {
+      Some(Value::String(text)) => text,
+      _ => String::new(),
+    };
+    let location = map.remove("location").filter(|v| *v != Value::Null);
+    Self { text, location }
+  }
+}
+
+/// The result of a `transform` call: the transformed code plus any generated
+/// source map and diagnostics.
+/// https://esbuild.github.io/api/#transform-api
+pub struct TransformResult {
+  pub code: String,
+  pub map: Option<String>,
+  pub warnings: Vec<Message>,
+  pub errors: Vec<Message>,
+}
+
+impl From<Value> for TransformResult {
+  fn from(value: Value) -> Self {
+    let Some(mut map) = into_map(value) else {
+      return Self {
+        code: String::new(),
+        map: None,
+        warnings: Vec::new(),
+        errors: Vec::new(),
+      };
+    };
+
+    let code = match map.remove("code") {
+      Some(Value::String(code)) => code,
+      Some(Value::Uint8Array(bytes)) => String::from_utf8(bytes).unwrap_or_default(),
+      _ => String::new(),
+    };
+    let map_field = match map.remove("map") {
+      Some(Value::String(map)) if !map.is_empty() => Some(map),
+      _ => None,
+    };
+    let messages = |key: &str, map: &mut BTreeMap<String, Value>| -> Vec<Message> {
+      match map.remove(key) {
+        Some(Value::Array(items)) => items.into_iter().map(Message::from).collect(),
+        _ => Vec::new(),
+      }
+    };
+    let warnings = messages("warnings", &mut map);
+    let errors = messages("errors", &mut map);
+
+    Self {
+      code,
+      map: map_field,
+      warnings,
+      errors,
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct BuildOptions {
+  /// Options shared with the transform API.
+  /// https://esbuild.github.io/api/#build-api
+  pub common: CommonOptions,
+  /// https://esbuild.github.io/api/#entry-points
+  pub entry_points: Vec<String>,
+  /// https://esbuild.github.io/api/#bundle
+  pub bundle: bool,
+  /// https://esbuild.github.io/api/#outdir
+  pub outdir: Option<String>,
+  /// https://esbuild.github.io/api/#splitting
+  pub splitting: bool,
+  /// https://esbuild.github.io/api/#external
+  pub external: Option<Vec<String>>,
+  /// https://esbuild.github.io/api/#metafile
+  pub metafile: bool,
+  /// https://esbuild.github.io/api/#write
+  pub write: bool,
+  /// https://esbuild.github.io/plugins/
+  pub plugins: Vec<Plugin>,
+}
+
+impl From<&BuildOptions> for Value {
+  fn from(options: &BuildOptions) -> Self {
+    let mut map = common_options_into_map(&options.common);
+
+    map.insert(
+      "entryPoints".to_string(),
+      Value::Array(
+        options
+          .entry_points
+          .iter()
+          .map(|path| Value::String(path.clone()))
+          .collect(),
+      ),
+    );
+    map.insert("bundle".to_string(), Value::Boolean(options.bundle));
+    if let Some(outdir) = &options.outdir {
+      map.insert("outdir".to_string(), Value::String(outdir.clone()));
+    }
+    map.insert("splitting".to_string(), Value::Boolean(options.splitting));
+    if let Some(external) = &options.external {
+      map.insert(
+        "external".to_string(),
+        Value::Array(external.iter().map(|path| Value::String(path.clone())).collect()),
+      );
+    }
+    map.insert("metafile".to_string(), Value::Boolean(options.metafile));
+    map.insert("write".to_string(), Value::Boolean(options.write));
+
+    if !options.plugins.is_empty() {
+      map.insert(
+        "plugins".to_string(),
+        Value::Array(options.plugins.iter().map(Value::from).collect()),
+      );
+    }
+
+    Value::Map(map)
+  }
+}
+
+fn filters<T>(entries: &[(Regex, T)]) -> Value {
+  Value::Array(
+    entries
+      .iter()
+      .map(|(filter, _)| {
+        let mut entry = BTreeMap::new();
+        entry.insert("filter".to_string(), Value::String(filter.as_str().to_string()));
+        Value::Map(entry)
+      })
+      .collect(),
+  )
+}
+
+impl From<&Plugin> for Value {
+  fn from(plugin: &Plugin) -> Self {
+    let mut map = BTreeMap::new();
+    map.insert("name".to_string(), Value::String(plugin.name.clone()));
+    map.insert("onResolve".to_string(), filters(&plugin.on_resolve));
+    map.insert("onLoad".to_string(), filters(&plugin.on_load));
+    Value::Map(map)
+  }
+}
+
+/// A single file produced by a build.
+pub struct OutputFile {
+  pub path: String,
+  pub contents: Vec<u8>,
+}
+
+impl From<Value> for OutputFile {
+  fn from(value: Value) -> Self {
+    let Some(mut map) = into_map(value) else {
+      return Self {
+        path: String::new(),
+        contents: Vec::new(),
+      };
+    };
+    let path = match map.remove("path") {
+      Some(Value::String(path)) => path,
+      _ => String::new(),
+    };
+    let contents = match map.remove("contents") {
+      Some(Value::Uint8Array(contents)) => contents,
+      _ => Vec::new(),
+    };
+    Self { path, contents }
+  }
+}
+
+/// The result of a `build` call.
+/// https://esbuild.github.io/api/#build-api
+pub struct BuildResult {
+  pub output_files: Vec<OutputFile>,
+  pub metafile: Option<String>,
+  pub warnings: Vec<Message>,
+  pub errors: Vec<Message>,
+}
+
+impl From<Value> for BuildResult {
+  fn from(value: Value) -> Self {
+    let Some(mut map) = into_map(value) else {
+      return Self {
+        output_files: Vec::new(),
+        metafile: None,
+        warnings: Vec::new(),
+        errors: Vec::new(),
+      };
+    };
+
+    let output_files = match map.remove("outputFiles") {
+      Some(Value::Array(items)) => items.into_iter().map(OutputFile::from).collect(),
+      _ => Vec::new(),
+    };
+    let metafile = match map.remove("metafile") {
+      Some(Value::String(metafile)) if !metafile.is_empty() => Some(metafile),
+      _ => None,
+    };
+    let messages = |key: &str, map: &mut BTreeMap<String, Value>| -> Vec<Message> {
+      match map.remove(key) {
+        Some(Value::Array(items)) => items.into_iter().map(Message::from).collect(),
+        _ => Vec::new(),
+      }
+    };
+    let warnings = messages("warnings", &mut map);
+    let errors = messages("errors", &mut map);
+
+    Self {
+      output_files,
+      metafile,
+      warnings,
+      errors,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn empty_common_options() -> CommonOptions {
+    CommonOptions {
+      sourcemap: None,
+      legal_comments: None,
+      source_root: None,
+      sources_content: None,
+      format: None,
+      global_name: String::new(),
+      target: None,
+      engines: None,
+      platform: None,
+      mangle_props: None,
+      reserve_props: None,
+      mangle_quoted: None,
+      mangle_cache: None,
+      drop: None,
+      minify: false,
+      minify_whitespace: false,
+      minify_identifiers: false,
+      minify_syntax: false,
+      charset: None,
+      tree_shaking: None,
+      ignore_annotations: false,
+      jsx: None,
+      jsx_factory: None,
+      jsx_fragment: None,
+      define: None,
+      pure: None,
+      keep_names: false,
+      color: None,
+      log_level: None,
+      log_limit: 0,
+    }
+  }
+
+  fn empty_transform_options() -> TransformOptions {
+    TransformOptions {
+      common: empty_common_options(),
+      tsconfig_raw: String::new(),
+      loader: None,
+      sourcefile: String::new(),
+      banner: String::new(),
+      footer: String::new(),
+    }
+  }
+
+  fn map_value(value: Value) -> BTreeMap<String, Value> {
+    match value {
+      Value::Map(map) => map,
+      other => panic!("expected Value::Map, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn test_enum_fields_flatten_to_canonical_string_tokens() {
+    let mut options = empty_transform_options();
+    options.common.format = Some(Format::IIFE);
+    options.common.target = Some(Target::ESNext);
+    options.common.platform = Some(Platform::Node);
+    options.loader = Some(Loader::TSX);
+
+    let map = map_value(Value::from(&options));
+    assert_eq!(map.get("format"), Some(&Value::String("iife".to_string())));
+    assert_eq!(map.get("target"), Some(&Value::String("esnext".to_string())));
+    assert_eq!(map.get("platform"), Some(&Value::String("node".to_string())));
+    assert_eq!(map.get("loader"), Some(&Value::String("tsx".to_string())));
+  }
+
+  #[test]
+  fn test_drop_and_pure_flatten_to_arrays() {
+    let mut options = empty_transform_options();
+    options.common.drop = Some(vec![Drop::Console, Drop::Debugger]);
+    options.common.pure = Some(vec!["console.log".to_string()]);
+
+    let map = map_value(Value::from(&options));
+    assert_eq!(
+      map.get("drop"),
+      Some(&Value::Array(vec![
+        Value::String("console".to_string()),
+        Value::String("debugger".to_string()),
+      ]))
+    );
+    assert_eq!(
+      map.get("pure"),
+      Some(&Value::Array(vec![Value::String("console.log".to_string())]))
+    );
+  }
+
+  #[test]
+  fn test_mangle_cache_flattens_to_map_with_false_for_none() {
+    let mut options = empty_transform_options();
+    let mut cache = HashMap::new();
+    cache.insert("a".to_string(), Some("_a".to_string()));
+    cache.insert("b".to_string(), None);
+    options.common.mangle_cache = Some(cache);
+
+    let map = map_value(Value::from(&options));
+    let Some(Value::Map(mangle_cache)) = map.get("mangleCache") else {
+      panic!("expected mangleCache to be a Value::Map");
+    };
+    assert_eq!(mangle_cache.get("a"), Some(&Value::String("_a".to_string())));
+    assert_eq!(mangle_cache.get("b"), Some(&Value::Boolean(false)));
+  }
+
+  #[test]
+  fn test_regex_fields_emit_their_source_string() {
+    let mut options = empty_transform_options();
+    options.common.mangle_props = Some(Regex::new("^_").unwrap());
+
+    let map = map_value(Value::from(&options));
+    assert_eq!(map.get("mangleProps"), Some(&Value::String("^_".to_string())));
+  }
+
+  #[test]
+  fn test_booleans_flatten_to_value_boolean() {
+    let mut options = empty_transform_options();
+    options.common.minify = true;
+    options.common.keep_names = true;
+
+    let map = map_value(Value::from(&options));
+    assert_eq!(map.get("minify"), Some(&Value::Boolean(true)));
+    assert_eq!(map.get("keepNames"), Some(&Value::Boolean(true)));
+  }
+}